@@ -0,0 +1,81 @@
+//! Recursive backup/restore of a remote directory tree. Directories are
+//! walked one level at a time and each file is streamed to or from disk in
+//! bounded chunks so mirroring a whole SD card doesn't require holding any
+//! file fully in memory.
+
+use async_std::fs;
+use async_std::path::{Path, PathBuf};
+use failure::Error;
+use futures::future::{BoxFuture, FutureExt};
+use futures::prelude::*;
+
+use crate::{Connection, FileType};
+
+/// Recursively downloads everything under `remote_root` on the device into
+/// `local_dir`, creating subdirectories as needed.
+pub fn backup_tree<'a>(
+    c: &'a mut Connection,
+    remote_root: &'a str,
+    local_dir: &'a Path,
+) -> BoxFuture<'a, Result<(), Error>> {
+    async move {
+        fs::create_dir_all(local_dir).await?;
+
+        for fi in c.list_files(remote_root).await? {
+            let remote_path = format!("{}/{}", remote_root.trim_end_matches('/'), fi.name);
+            let local_path = local_dir.join(&fi.name);
+
+            match fi.ty {
+                FileType::Dir => {
+                    backup_tree(c, &remote_path, &local_path).await?;
+                }
+                FileType::File => {
+                    let mut file = fs::File::create(&local_path).await?;
+                    let mut chunks = c.download_file(&remote_path).await?;
+                    while let Some(chunk) = chunks.next().await {
+                        file.write_all(&chunk?).await?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+    .boxed()
+}
+
+/// Recursively uploads everything under `local_dir` on disk to
+/// `remote_root` on the device, creating remote directories as needed.
+pub fn restore_tree<'a>(
+    c: &'a mut Connection,
+    local_dir: &'a Path,
+    remote_root: &'a str,
+) -> BoxFuture<'a, Result<(), Error>> {
+    async move {
+        c.make_dir(remote_root).await?;
+
+        let mut entries = fs::read_dir(local_dir).await?;
+        while let Some(entry) = entries.next().await {
+            let entry = entry?;
+            let local_path: PathBuf = entry.path();
+            let name = local_path
+                .file_name()
+                .ok_or_else(|| failure::format_err!("can't parse file name from {:?}", local_path))?
+                .to_string_lossy()
+                .to_string();
+            let remote_path = format!("{}/{}", remote_root.trim_end_matches('/'), name);
+
+            let metadata = fs::metadata(&local_path).await?;
+            if metadata.is_dir() {
+                restore_tree(c, &local_path, &remote_path).await?;
+            } else {
+                let size = metadata.len();
+                let file = fs::File::open(&local_path).await?;
+                c.put_file_stream(&remote_path, size, file).await?;
+            }
+        }
+
+        Ok(())
+    }
+    .boxed()
+}
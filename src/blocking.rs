@@ -0,0 +1,103 @@
+use async_std::task;
+use async_trait::async_trait;
+use failure::Error;
+
+use crate::{Connection, FileInfo, Usb2SnesClient};
+
+/// Synchronous wrapper around [`Connection`] for callers that don't already
+/// have an async runtime of their own (tools, tests). Each call runs the
+/// underlying async operation to completion on an internal `async-std`
+/// executor before returning. Enabled by the `blocking` cargo feature.
+pub struct BlockingConnection {
+    inner: Connection,
+}
+
+impl BlockingConnection {
+    pub fn new(addr: &str) -> Result<BlockingConnection, Error> {
+        let inner = task::block_on(Connection::new(addr))?;
+        Ok(BlockingConnection { inner })
+    }
+
+    pub fn close(self) -> Result<(), Error> {
+        task::block_on(self.inner.close())
+    }
+
+    pub fn get_device_list(&mut self) -> Result<Vec<String>, Error> {
+        task::block_on(self.inner.get_device_list())
+    }
+
+    pub fn attach(&mut self, device: &str) -> Result<(), Error> {
+        task::block_on(self.inner.attach(device))
+    }
+
+    pub fn get_info(&mut self) -> Result<Vec<String>, Error> {
+        task::block_on(self.inner.get_info())
+    }
+
+    pub fn list_files(&mut self, path: &str) -> Result<Vec<FileInfo>, Error> {
+        task::block_on(self.inner.list_files(path))
+    }
+
+    pub fn put_file(&mut self, path: &str, data: &[u8]) -> Result<(), Error> {
+        task::block_on(self.inner.put_file(path, data))
+    }
+
+    pub fn rm(&mut self, path: &str) -> Result<(), Error> {
+        task::block_on(self.inner.rm(path))
+    }
+
+    pub fn read_mem(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Error> {
+        task::block_on(self.inner.read_mem(addr, data))
+    }
+
+    pub fn write_mem(&mut self, addr: u32, data: &[u8]) -> Result<(), Error> {
+        task::block_on(self.inner.write_mem(addr, data))
+    }
+
+    pub fn write_mem_confirmed(&mut self, addr: u32, data: &[u8]) -> Result<(), Error> {
+        task::block_on(self.inner.write_mem_confirmed(addr, data))
+    }
+}
+
+// `Usb2SnesClient` methods are `async fn`s: they must delegate to `inner`'s
+// own async methods directly rather than the synchronous wrappers above,
+// which `task::block_on` internally and would stall whatever executor is
+// driving the surrounding `.await` if called through this trait.
+#[async_trait]
+impl Usb2SnesClient for BlockingConnection {
+    async fn get_device_list(&mut self) -> Result<Vec<String>, Error> {
+        self.inner.get_device_list().await
+    }
+
+    async fn attach(&mut self, device: &str) -> Result<(), Error> {
+        self.inner.attach(device).await
+    }
+
+    async fn get_info(&mut self) -> Result<Vec<String>, Error> {
+        self.inner.get_info().await
+    }
+
+    async fn list_files(&mut self, path: &str) -> Result<Vec<FileInfo>, Error> {
+        self.inner.list_files(path).await
+    }
+
+    async fn put_file(&mut self, path: &str, data: &[u8]) -> Result<(), Error> {
+        self.inner.put_file(path, data).await
+    }
+
+    async fn rm(&mut self, path: &str) -> Result<(), Error> {
+        self.inner.rm(path).await
+    }
+
+    async fn read_mem(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Error> {
+        self.inner.read_mem(addr, data).await
+    }
+
+    async fn write_mem(&mut self, addr: u32, data: &[u8]) -> Result<(), Error> {
+        self.inner.write_mem(addr, data).await
+    }
+
+    async fn write_mem_confirmed(&mut self, addr: u32, data: &[u8]) -> Result<(), Error> {
+        self.inner.write_mem_confirmed(addr, data).await
+    }
+}
@@ -0,0 +1,56 @@
+//! TOML configuration file (`~/.config/usb2snes.toml` by default): the
+//! websocket server address, a preferred default device, and named game
+//! profiles that commands like the memory map tracker can select by name.
+
+use failure::{format_err, Error};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::memmap::Profile;
+
+fn default_address() -> String {
+    "ws://localhost:8080".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    #[serde(default = "default_address")]
+    pub address: String,
+
+    #[serde(default)]
+    pub device: Option<String>,
+
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            address: default_address(),
+            device: None,
+            profiles: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config from `path`, falling back to defaults if the file
+    /// doesn't exist.
+    pub fn load(path: &Path) -> Result<Config, Error> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let text = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    /// `~/.config/usb2snes.toml`, used when `--config` isn't given.
+    pub fn default_path() -> Result<PathBuf, Error> {
+        let dir = dirs::config_dir()
+            .ok_or_else(|| format_err!("could not determine config directory"))?;
+        Ok(dir.join("usb2snes.toml"))
+    }
+}
@@ -1,10 +1,24 @@
 use async_std;
 use async_std::net::TcpStream;
+use async_trait::async_trait;
 use async_tungstenite::{async_std::connect_async, tungstenite::Message, WebSocketStream};
+use bytes::Bytes;
 use failure::{format_err, Error};
 use futures::prelude::*;
+use futures::stream;
 use serde::{Deserialize, Serialize};
 use serde_json;
+use std::pin::Pin;
+
+#[cfg(feature = "blocking")]
+mod blocking;
+#[cfg(feature = "blocking")]
+pub use blocking::BlockingConnection;
+
+pub mod backup;
+pub mod config;
+pub mod memmap;
+pub mod proxy;
 
 #[derive(Debug, PartialEq)]
 pub enum FileType {
@@ -19,43 +33,51 @@ pub struct FileInfo {
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-enum Opcode {
+pub(crate) enum Opcode {
     Attach,
+    Boot,
     DeviceList,
     GetAddress,
+    GetFile,
     Info,
     List,
+    MakeDir,
+    Menu,
+    PutAddress,
     PutFile,
+    PutIPS,
     Remove,
+    Reset,
+    Stream,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-enum Space {
+pub(crate) enum Space {
     #[serde(rename = "SNES")]
     Snes,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-struct Request {
+pub(crate) struct Request {
     #[serde(rename = "Opcode")]
-    opcode: Opcode,
+    pub(crate) opcode: Opcode,
 
     #[serde(rename = "Space")]
-    space: Space,
+    pub(crate) space: Space,
 
     #[serde(rename = "Flags")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    flags: Option<Vec<String>>,
+    pub(crate) flags: Option<Vec<String>>,
 
     #[serde(rename = "Operands")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    ops: Option<Vec<String>>,
+    pub(crate) ops: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-struct Results {
+pub(crate) struct Results {
     #[serde(rename = "Results")]
-    results: Vec<String>,
+    pub(crate) results: Vec<String>,
 }
 
 pub struct Connection {
@@ -178,6 +200,99 @@ impl Connection {
         Ok(())
     }
 
+    /// Like [`put_file`](Connection::put_file) but streams `reader` in
+    /// bounded 1 KiB chunks instead of requiring the whole file in memory,
+    /// so large transfers (backups) don't need to buffer the full payload.
+    pub async fn put_file_stream<R>(
+        &mut self,
+        path: &str,
+        size: u64,
+        mut reader: R,
+    ) -> Result<(), Error>
+    where
+        R: async_std::io::Read + Unpin,
+    {
+        let req = Request {
+            opcode: Opcode::PutFile,
+            space: Space::Snes,
+            flags: None,
+            ops: Some(vec![path.to_string(), format!("{:X}", size)]),
+        };
+        self.send(&req).await?;
+
+        let mut buf = vec![0u8; 1024];
+        loop {
+            let n = async_std::io::ReadExt::read(&mut reader, &mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            self.ws.send(Message::Binary(buf[..n].to_vec())).await?;
+            self.ws.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn make_dir(&mut self, path: &str) -> Result<(), Error> {
+        let req = Request {
+            opcode: Opcode::MakeDir,
+            space: Space::Snes,
+            flags: None,
+            ops: Some(vec![path.to_string()]),
+        };
+        self.send(&req).await?;
+        self.ws.flush().await?;
+        Ok(())
+    }
+
+    /// Downloads `path` from the device, returning a stream of the binary
+    /// chunks as they arrive rather than buffering the whole file. Boxed
+    /// and pinned so callers can drive it with `StreamExt::next` without
+    /// pinning it themselves.
+    pub async fn download_file<'a>(
+        &'a mut self,
+        path: &str,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Bytes, Error>> + 'a>>, Error> {
+        let req = Request {
+            opcode: Opcode::GetFile,
+            space: Space::Snes,
+            flags: None,
+            ops: Some(vec![path.to_string()]),
+        };
+        self.send(&req).await?;
+
+        let size_str = self
+            .recv()
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format_err!("no size returned for {}", path))?;
+        let size = u64::from_str_radix(&size_str, 16)?;
+
+        Ok(Box::pin(stream::unfold(
+            (self, 0u64),
+            move |(conn, offset)| async move {
+                if offset >= size {
+                    return None;
+                }
+
+                while let Some(msg) = conn.ws.next().await {
+                    let msg = match msg {
+                        Ok(msg) => msg,
+                        Err(e) => return Some((Err(e.into()), (conn, offset))),
+                    };
+                    if msg.is_binary() {
+                        let data = Bytes::from(msg.into_data());
+                        let new_offset = offset + data.len() as u64;
+                        return Some((Ok(data), (conn, new_offset)));
+                    }
+                }
+
+                None
+            },
+        )))
+    }
+
     pub async fn rm(&mut self, path: &str) -> Result<(), Error> {
         let req = Request {
             opcode: Opcode::Remove,
@@ -190,6 +305,43 @@ impl Connection {
         Ok(())
     }
 
+    pub async fn write_mem(&mut self, addr: u32, data: &[u8]) -> Result<(), Error> {
+        let req = Request {
+            opcode: Opcode::PutAddress,
+            space: Space::Snes,
+            flags: None,
+            ops: Some(vec![format!("{:X}", addr), format!("{:X}", data.len())]),
+        };
+
+        self.send(&req).await?;
+
+        for chunk in data.chunks(1024) {
+            self.ws.send(Message::Binary(chunk.to_vec())).await?;
+            self.ws.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes `data` to `addr` and reads it back to confirm the device
+    /// actually stored what was sent, returning an error if the bytes
+    /// don't match.
+    pub async fn write_mem_confirmed(&mut self, addr: u32, data: &[u8]) -> Result<(), Error> {
+        self.write_mem(addr, data).await?;
+
+        let mut readback = vec![0; data.len()];
+        self.read_mem(addr, &mut readback).await?;
+
+        if readback != data {
+            return Err(format_err!(
+                "write_mem_confirmed: read-back mismatch at {:#x}",
+                addr
+            ));
+        }
+
+        Ok(())
+    }
+
     pub async fn read_mem(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Error> {
         let mut offset = 0;
         let len = data.len();
@@ -218,6 +370,62 @@ impl Connection {
     }
 }
 
+/// Common set of operations exposed by the usb2snes protocol, implemented
+/// by both the async [`Connection`] and, behind the `blocking` feature,
+/// [`BlockingConnection`]. Lets generic async code target either; the
+/// trait's methods are `async`, so callers still `.await` them.
+#[async_trait]
+pub trait Usb2SnesClient {
+    async fn get_device_list(&mut self) -> Result<Vec<String>, Error>;
+    async fn attach(&mut self, device: &str) -> Result<(), Error>;
+    async fn get_info(&mut self) -> Result<Vec<String>, Error>;
+    async fn list_files(&mut self, path: &str) -> Result<Vec<FileInfo>, Error>;
+    async fn put_file(&mut self, path: &str, data: &[u8]) -> Result<(), Error>;
+    async fn rm(&mut self, path: &str) -> Result<(), Error>;
+    async fn read_mem(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Error>;
+    async fn write_mem(&mut self, addr: u32, data: &[u8]) -> Result<(), Error>;
+    async fn write_mem_confirmed(&mut self, addr: u32, data: &[u8]) -> Result<(), Error>;
+}
+
+#[async_trait]
+impl Usb2SnesClient for Connection {
+    async fn get_device_list(&mut self) -> Result<Vec<String>, Error> {
+        Connection::get_device_list(self).await
+    }
+
+    async fn attach(&mut self, device: &str) -> Result<(), Error> {
+        Connection::attach(self, device).await
+    }
+
+    async fn get_info(&mut self) -> Result<Vec<String>, Error> {
+        Connection::get_info(self).await
+    }
+
+    async fn list_files(&mut self, path: &str) -> Result<Vec<FileInfo>, Error> {
+        Connection::list_files(self, path).await
+    }
+
+    async fn put_file(&mut self, path: &str, data: &[u8]) -> Result<(), Error> {
+        Connection::put_file(self, path, data).await
+    }
+
+    async fn rm(&mut self, path: &str) -> Result<(), Error> {
+        Connection::rm(self, path).await
+    }
+
+    async fn read_mem(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Error> {
+        Connection::read_mem(self, addr, data).await
+    }
+
+    async fn write_mem(&mut self, addr: u32, data: &[u8]) -> Result<(), Error> {
+        Connection::write_mem(self, addr, data).await
+    }
+
+    async fn write_mem_confirmed(&mut self, addr: u32, data: &[u8]) -> Result<(), Error> {
+        Connection::write_mem_confirmed(self, addr, data).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -257,6 +465,23 @@ mod tests {
   "Operands": [
     "SD2SNES COM3"
   ]
+}"#
+                .to_string(),
+            },
+            ReqTest {
+                req: Request {
+                    opcode: Opcode::PutAddress,
+                    space: Space::Snes,
+                    flags: None,
+                    ops: Some(vec!["F50010".to_string(), "4".to_string()]),
+                },
+                json: r#"{
+  "Opcode": "PutAddress",
+  "Space": "SNES",
+  "Operands": [
+    "F50010",
+    "4"
+  ]
 }"#
                 .to_string(),
             },
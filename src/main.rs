@@ -1,8 +1,6 @@
 use async_std;
 use byteorder::{LittleEndian, ReadBytesExt};
 use failure::{format_err, Error};
-use num::{FromPrimitive, ToPrimitive};
-use num_derive::{FromPrimitive, ToPrimitive};
 use parse_int::parse;
 use std::fs::File;
 use std::io::prelude::*;
@@ -10,96 +8,9 @@ use std::io::Cursor;
 use std::num::ParseIntError;
 use std::path::PathBuf;
 use structopt::StructOpt;
-use strum::IntoEnumIterator;
-use strum_macros::EnumIter;
+use usb2snes::config::Config;
 use usb2snes::{Connection, FileType};
 
-#[derive(Debug, EnumIter, FromPrimitive, ToPrimitive)]
-enum KeyItem {
-    Package = 0x00,
-    SandRuby = 0x01,
-    LegendSword = 0x02,
-    BaronKey = 0x03,
-    TwinHarp = 0x04,
-    EarthCrystal = 0x05,
-    MagmaKey = 0x06,
-    TowerKey = 0x07,
-    Hook = 0x08,
-    LucaKey = 0x09,
-    DarknessCrystal = 0x0a,
-    RatTail = 0x0b,
-    Adamant = 0x0c,
-    Pan = 0x0d,
-    Spoon = 0x0e,
-    PinkTail = 0x0f,
-    Crystal = 0x10,
-}
-
-#[repr(u16)]
-#[derive(Debug, FromPrimitive, ToPrimitive)]
-enum Location {
-    StartingItem = 0x20,
-    Antlion = 0x21,
-    DefendingFabul = 0x22,
-    MtOrdeals = 0x23,
-    BaronInn = 0x24,
-    BaronCastle = 0x25,
-    EdwardInToroia = 0x26,
-    CaveMagnes = 0x27,
-    TowerOfZot = 0x28,
-    LowerBabIlBoss = 0x29,
-    SuperCannon = 0x2a,
-    Luca = 0x2b, // aka DwarfCastle
-    SealedCave = 0x2c,
-    FeymarchChest = 0x2d,
-    RatTail = 0x2e,
-    YangsWife = 0x2f,
-    YangsWifePan = 0x30,
-    FeymarchQueen = 0x31,
-    FeymarchKing = 0x32,
-    Odin = 0x33,
-    Sylphs = 0x34,
-    CaveBahamut = 0x35,
-    PaleDim = 0x36,
-    Wyvern = 0x37,
-    Plauge = 0x38,
-    DLunar1 = 0x39,
-    DLunar2 = 0x3a,
-    Ogopogo = 0x3b,
-    TowerOfZotTrappedChest = 0x3c,
-    EblanTrappedChest1 = 0x3d,
-    EblanTrappedChest2 = 0x3e,
-    EblanTrappedChest3 = 0x3f,
-    LowerBabIlTappedChest1 = 0x40,
-    LowerBabIlTappedChest2 = 0x41,
-    LowerBabIlTappedChest3 = 0x42,
-    LowerBabIlTappedChest4 = 0x43,
-    CaveEblanTrappedChest = 0x44,
-    UpperBabIlTrappedChest = 0x45,
-    CaveOfSummonsTrappedChest = 0x46,
-    SyplhCaveTrappedChest1 = 0x47,
-    SyplhCaveTrappedChest2 = 0x48,
-    SyplhCaveTrappedChest3 = 0x49,
-    SyplhCaveTrappedChest4 = 0x4a,
-    SyplhCaveTrappedChest5 = 0x4b,
-    SyplhCaveTrappedChest6 = 0x4c,
-    SyplhCaveTrappedChest7 = 0x4d,
-    GiantOfBabIlTrappedChest = 0x4e,
-    LunarPathTrappedChest = 0x4f,
-    LunarCoreTrappedChest1 = 0x50,
-    LunarCoreTrappedChest2 = 0x51,
-    LunarCoreTrappedChest3 = 0x52,
-    LunarCoreTrappedChest4 = 0x53,
-    LunarCoreTrappedChest5 = 0x54,
-    LunarCoreTrappedChest6 = 0x55,
-    LunarCoreTrappedChest7 = 0x56,
-    LunarCoreTrappedChest8 = 0x57,
-    LunarCoreTrappedChest9 = 0x58,
-    RydiasMom = 0x59,
-    FallenGolbez = 0x5a,
-    ObjectiveCompletion = 0x5d,
-}
-
 fn parse_num(src: &str) -> Result<u32, ParseIntError> {
     parse::<u32>(src)
 }
@@ -128,11 +39,42 @@ enum Command {
         len: u32,
     },
     Flags,
-    Track,
+    Track {
+        #[structopt(long)]
+        profile: Option<String>,
+    },
+    Backup {
+        remote_root: String,
+
+        #[structopt(parse(from_os_str))]
+        local_dir: PathBuf,
+    },
+    Restore {
+        #[structopt(parse(from_os_str))]
+        local_dir: PathBuf,
+
+        remote_root: String,
+    },
+    Proxy {
+        #[structopt(long, default_value = "127.0.0.1:8081")]
+        listen: String,
+
+        #[structopt(long, default_value = "ws://localhost:8080")]
+        upstream: String,
+    },
 }
 
 #[derive(StructOpt)]
 struct Opt {
+    /// Path to the TOML config file. Defaults to ~/.config/usb2snes.toml.
+    #[structopt(long, parse(from_os_str))]
+    config: Option<PathBuf>,
+
+    /// Overrides the config file's websocket server address.
+    #[structopt(long)]
+    server: Option<String>,
+
+    /// Overrides the config file's default device.
     #[structopt(long)]
     device: Option<String>,
 
@@ -216,26 +158,63 @@ async fn handle_read(c: &mut Connection, addr: u32, len: u32) -> Result<(), Erro
     Ok(())
 }
 
-async fn handle_track(c: &mut Connection) -> Result<(), Error> {
-    for ki in KeyItem::iter() {
-        let mut buf = [0; 2];
-        let index = ki.to_u32().unwrap();
-        c.read_mem(0xe07080 + 2 * index, &mut buf).await?;
-        let loc_val = Cursor::new(buf).read_u16::<LittleEndian>()?;
-        let loc = Location::from_u16(loc_val);
-        let loc_str = match loc {
-            Some(l) => format!("{:?}", l),
-            None => "".to_string(),
-        };
-        println!("{:?} = {}", ki, loc_str);
-    }
-    Ok(())
+async fn handle_track(
+    c: &mut Connection,
+    config: &Config,
+    profile_name: Option<String>,
+) -> Result<(), Error> {
+    let profile = match profile_name {
+        Some(name) => config
+            .profiles
+            .get(&name)
+            .ok_or_else(|| format_err!("no profile named {:?} in config", name))?,
+        None => usb2snes::memmap::detect_profile(c, &config.profiles)
+            .await?
+            .ok_or_else(|| format_err!("could not auto-detect a profile; pass --profile"))?,
+    };
+
+    usb2snes::memmap::track(c, profile).await
+}
+
+async fn handle_backup(
+    c: &mut Connection,
+    remote_root: String,
+    local_dir: PathBuf,
+) -> Result<(), Error> {
+    let local_dir = async_std::path::Path::new(&local_dir);
+    println!("{} -> {}", remote_root, local_dir.display());
+    usb2snes::backup::backup_tree(c, &remote_root, local_dir).await
+}
+
+async fn handle_restore(
+    c: &mut Connection,
+    local_dir: PathBuf,
+    remote_root: String,
+) -> Result<(), Error> {
+    let local_dir = async_std::path::Path::new(&local_dir);
+    println!("{} -> {}", local_dir.display(), remote_root);
+    usb2snes::backup::restore_tree(c, local_dir, &remote_root).await
 }
 
 async fn run(opt: Opt) -> Result<(), Error> {
-    let mut c = usb2snes::Connection::new("ws://localhost:8080").await?;
+    if let Command::Proxy {
+        ref listen,
+        ref upstream,
+    } = opt.cmd
+    {
+        return usb2snes::proxy::run(listen, upstream).await;
+    }
+
+    let config_path = match &opt.config {
+        Some(p) => p.clone(),
+        None => Config::default_path()?,
+    };
+    let config = Config::load(&config_path)?;
+
+    let address = opt.server.unwrap_or_else(|| config.address.clone());
+    let mut c = usb2snes::Connection::new(&address).await?;
 
-    let dev = match opt.device {
+    let dev = match opt.device.or_else(|| config.device.clone()) {
         Some(d) => d,
         None => {
             let devs = c.get_device_list().await?;
@@ -253,7 +232,16 @@ async fn run(opt: Opt) -> Result<(), Error> {
         Command::Put { dest_dir, files } => handle_put(&mut c, dest_dir, files).await?,
         Command::Rm { files } => handle_rm(&mut c, files).await?,
         Command::Read { addr, len } => handle_read(&mut c, addr, len).await?,
-        Command::Track => handle_track(&mut c).await?,
+        Command::Track { profile } => handle_track(&mut c, &config, profile).await?,
+        Command::Backup {
+            remote_root,
+            local_dir,
+        } => handle_backup(&mut c, remote_root, local_dir).await?,
+        Command::Restore {
+            local_dir,
+            remote_root,
+        } => handle_restore(&mut c, local_dir, remote_root).await?,
+        Command::Proxy { .. } => unreachable!("handled before connecting to a device"),
     };
 
     Ok(())
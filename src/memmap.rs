@@ -0,0 +1,180 @@
+//! Data-driven memory-map tracker. A [`Profile`] declares the fields of a
+//! particular game's save/tracker state (address, encoding, and optional
+//! value labels) so the tracker isn't hardcoded to one randomizer's memory
+//! layout.
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use failure::{format_err, Error};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::Connection;
+
+/// SNES header title field: 21 bytes at the LoROM header offset.
+const ROM_TITLE_ADDR: u32 = 0x007fc0;
+const ROM_TITLE_LEN: usize = 21;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    U8,
+    U16le,
+    U16be,
+    U32le,
+    Bytes,
+}
+
+fn deserialize_hex_addr<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    parse_int::parse::<u32>(&s).map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Field {
+    pub name: String,
+
+    #[serde(deserialize_with = "deserialize_hex_addr")]
+    pub addr: u32,
+
+    pub ty: FieldType,
+
+    #[serde(default)]
+    pub len: Option<usize>,
+
+    #[serde(default)]
+    pub values: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Profile {
+    /// ROM title (as found in the SNES header) used to auto-select this
+    /// profile; optional since it can also be chosen with `--profile`.
+    #[serde(default)]
+    pub rom_title: Option<String>,
+
+    pub fields: Vec<Field>,
+}
+
+enum FieldValue {
+    Number(u64),
+    Bytes(Vec<u8>),
+}
+
+impl FieldValue {
+    fn raw_label(&self) -> String {
+        match self {
+            FieldValue::Number(n) => n.to_string(),
+            FieldValue::Bytes(b) => b.iter().map(|byte| format!("{:02x}", byte)).collect(),
+        }
+    }
+}
+
+/// Decodes a field's raw bytes according to its type. Pulled out of
+/// [`read_field`] so the decoding itself can be unit tested without a live
+/// connection.
+fn decode(ty: FieldType, buf: &[u8]) -> FieldValue {
+    match ty {
+        FieldType::U8 => FieldValue::Number(buf[0] as u64),
+        FieldType::U16le => FieldValue::Number(LittleEndian::read_u16(buf) as u64),
+        FieldType::U16be => FieldValue::Number(BigEndian::read_u16(buf) as u64),
+        FieldType::U32le => FieldValue::Number(LittleEndian::read_u32(buf) as u64),
+        FieldType::Bytes => FieldValue::Bytes(buf.to_vec()),
+    }
+}
+
+/// Looks up the display label for `value` in `field.values`, falling back
+/// to the raw numeric/hex value when there's no matching entry.
+fn label_for(field: &Field, value: &FieldValue) -> String {
+    let raw = value.raw_label();
+    field.values.get(&raw).cloned().unwrap_or(raw)
+}
+
+async fn read_field(c: &mut Connection, field: &Field) -> Result<FieldValue, Error> {
+    let len = match field.ty {
+        FieldType::U8 => 1,
+        FieldType::U16le | FieldType::U16be => 2,
+        FieldType::U32le => 4,
+        FieldType::Bytes => field
+            .len
+            .ok_or_else(|| format_err!("field {:?} is type bytes but has no len", field.name))?,
+    };
+
+    let mut buf = vec![0u8; len];
+    c.read_mem(field.addr, &mut buf).await?;
+    Ok(decode(field.ty, &buf))
+}
+
+/// Reads every field in `profile` and prints `name = label`, falling back
+/// to the raw numeric/hex value when `values` has no matching entry.
+pub async fn track(c: &mut Connection, profile: &Profile) -> Result<(), Error> {
+    for field in &profile.fields {
+        let value = read_field(c, field).await?;
+        println!("{} = {}", field.name, label_for(field, &value));
+    }
+    Ok(())
+}
+
+/// Reads the ROM title out of the SNES header.
+pub async fn read_rom_title(c: &mut Connection) -> Result<String, Error> {
+    let mut buf = [0u8; ROM_TITLE_LEN];
+    c.read_mem(ROM_TITLE_ADDR, &mut buf).await?;
+    Ok(String::from_utf8_lossy(&buf).trim().to_string())
+}
+
+/// Picks the profile whose `rom_title` matches the attached ROM, if any.
+pub async fn detect_profile<'a>(
+    c: &mut Connection,
+    profiles: &'a HashMap<String, Profile>,
+) -> Result<Option<&'a Profile>, Error> {
+    let title = read_rom_title(c).await?;
+    Ok(profiles
+        .values()
+        .find(|p| p.rom_title.as_deref() == Some(title.as_str())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field_with_values(ty: FieldType, values: &[(&str, &str)]) -> Field {
+        Field {
+            name: "Test".to_string(),
+            addr: 0,
+            ty,
+            len: None,
+            values: values
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn decode_each_field_type() {
+        assert_eq!(decode(FieldType::U8, &[0x42]).raw_label(), "66");
+        assert_eq!(decode(FieldType::U16le, &[0x01, 0x00]).raw_label(), "1");
+        assert_eq!(decode(FieldType::U16be, &[0x00, 0x01]).raw_label(), "1");
+        assert_eq!(
+            decode(FieldType::U32le, &[0x01, 0x00, 0x00, 0x00]).raw_label(),
+            "1"
+        );
+        assert_eq!(decode(FieldType::Bytes, &[0xde, 0xad]).raw_label(), "dead");
+    }
+
+    #[test]
+    fn label_for_uses_values_table_when_present() {
+        let field = field_with_values(FieldType::U8, &[("1", "Package")]);
+        let value = decode(FieldType::U8, &[0x01]);
+        assert_eq!(label_for(&field, &value), "Package");
+    }
+
+    #[test]
+    fn label_for_falls_back_to_raw_value_when_missing() {
+        let field = field_with_values(FieldType::U8, &[("1", "Package")]);
+        let value = decode(FieldType::U8, &[0x02]);
+        assert_eq!(label_for(&field, &value), "2");
+    }
+}
@@ -0,0 +1,122 @@
+//! Man-in-the-middle proxy for debugging against real hardware: listen
+//! locally, forward every frame to the real usb2snes server unmodified,
+//! and log each text and binary frame on the way through so the traffic can
+//! be inspected.
+
+use async_std::net::{TcpListener, TcpStream};
+use async_std::task;
+use async_tungstenite::accept_async;
+use async_tungstenite::tungstenite::{self, Message};
+use failure::Error;
+use futures::prelude::*;
+
+use crate::{Request, Results};
+
+enum Direction {
+    ClientToServer,
+    ServerToClient,
+}
+
+impl Direction {
+    fn arrow(&self) -> &'static str {
+        match self {
+            Direction::ClientToServer => "-->",
+            Direction::ServerToClient => "<--",
+        }
+    }
+}
+
+/// Listens on `listen_addr` and forwards every connection to `upstream`
+/// (the real usb2snes server), logging each text and binary frame in both
+/// directions. Other frames (ping, pong, close) are forwarded without being
+/// logged.
+pub async fn run(listen_addr: &str, upstream: &str) -> Result<(), Error> {
+    let listener = TcpListener::bind(listen_addr).await?;
+    println!(
+        "proxy listening on {}, forwarding to {}",
+        listen_addr, upstream
+    );
+
+    let mut incoming = listener.incoming();
+    while let Some(stream) = incoming.next().await {
+        let stream = stream?;
+        let upstream = upstream.to_string();
+        task::spawn(async move {
+            if let Err(e) = handle_conn(stream, &upstream).await {
+                eprintln!("proxy connection error: {}", e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+async fn handle_conn(stream: TcpStream, upstream_addr: &str) -> Result<(), Error> {
+    let client_ws = accept_async(stream).await?;
+    let (upstream_ws, _) = async_tungstenite::async_std::connect_async(upstream_addr).await?;
+
+    let (client_write, client_read) = client_ws.split();
+    let (upstream_write, upstream_read) = upstream_ws.split();
+
+    let client_to_upstream = forward(client_read, upstream_write, Direction::ClientToServer);
+    let upstream_to_client = forward(upstream_read, client_write, Direction::ServerToClient);
+
+    future::try_join(client_to_upstream, upstream_to_client).await?;
+    Ok(())
+}
+
+async fn forward<S, D>(mut src: S, mut dst: D, dir: Direction) -> Result<(), Error>
+where
+    S: Stream<Item = Result<Message, tungstenite::Error>> + Unpin,
+    D: Sink<Message, Error = tungstenite::Error> + Unpin,
+{
+    while let Some(msg) = src.next().await {
+        let msg = msg?;
+        log_message(&dir, &msg);
+        dst.send(msg).await?;
+    }
+    Ok(())
+}
+
+fn log_message(dir: &Direction, msg: &Message) {
+    match msg {
+        Message::Text(text) => println!("{} {}", dir.arrow(), pretty_json(text)),
+        Message::Binary(data) => println!(
+            "{} {} byte binary frame\n{}",
+            dir.arrow(),
+            data.len(),
+            hexdump(data)
+        ),
+        _ => {}
+    }
+}
+
+/// Pretty-prints `text` as a `Request` or `Results`, the two shapes this
+/// client itself speaks. A sniffed session can carry messages outside those
+/// types (e.g. an opcode missing from `Opcode`, or a space other than
+/// `SNES`), so anything that doesn't fit either typed shape is still parsed
+/// and pretty-printed as generic JSON rather than being dumped raw.
+fn pretty_json(text: &str) -> String {
+    if let Ok(req) = serde_json::from_str::<Request>(text) {
+        return serde_json::to_string_pretty(&req).unwrap_or_else(|_| text.to_string());
+    }
+    if let Ok(res) = serde_json::from_str::<Results>(text) {
+        return serde_json::to_string_pretty(&res).unwrap_or_else(|_| text.to_string());
+    }
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(text) {
+        return serde_json::to_string_pretty(&value).unwrap_or_else(|_| text.to_string());
+    }
+    text.to_string()
+}
+
+fn hexdump(data: &[u8]) -> String {
+    let mut out = String::new();
+    for (i, chunk) in data.chunks(16).enumerate() {
+        out.push_str(&format!("{:08x}  ", i * 16));
+        for b in chunk {
+            out.push_str(&format!("{:02x} ", b));
+        }
+        out.push('\n');
+    }
+    out
+}